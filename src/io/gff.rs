@@ -4,10 +4,10 @@
 // except according to those terms.
 
 
-//! GFF3 format reading and writing.
+//! GFF3, GFF2 and GTF2 format reading and writing.
 //!
-//! GFF2 definition : http://gmod.org/wiki/GFF2#The_GFF2_File_Format (not yet support)
-//! GTF2 definition : http://mblab.wustl.edu/GTF2.html (not yet support)
+//! GFF2 definition : http://gmod.org/wiki/GFF2#The_GFF2_File_Format
+//! GTF2 definition : http://mblab.wustl.edu/GTF2.html
 //! GFF3 definition : http://gmod.org/wiki/GFF3#GFF3_Format
 //!
 //! # Example
@@ -15,49 +15,154 @@
 //! ```
 //! use std::io;
 //! use bio::io::gff;
-//! let reader = gff::Reader::new(io::stdin());
+//! let reader = gff::Reader::new(io::stdin(), gff::GffType::GFF3);
 //! ```
 
 use std::io;
 use std::fs;
+use std::fmt;
+use std::error;
 use std::path::Path;
 use std::convert::AsRef;
 use std::collections::HashMap;
 
 use csv;
+use multimap::MultiMap;
+use percent_encoding::{percent_decode, percent_encode, define_encode_set, SIMPLE_ENCODE_SET};
 
 use io::Strand;
 
+define_encode_set! {
+    /// Characters reserved by the GFF3 attributes column.
+    pub GFF3_ATTRIBUTE_ENCODE_SET = [SIMPLE_ENCODE_SET] | {';', '=', '&', ',', '\t', '\n', '%'}
+}
+
+/// The three members of the GFF family. They only differ in how the last
+/// (attributes) column is encoded; everything else is shared.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GffType {
+    /// http://gmod.org/wiki/GFF2#The_GFF2_File_Format
+    GFF2,
+    /// http://mblab.wustl.edu/GTF2.html
+    GTF2,
+    /// http://gmod.org/wiki/GFF3#GFF3_Format
+    GFF3,
+}
+
+impl GffType {
+    /// The `(field_separator, key_value_separator)` pair used to encode and
+    /// decode the attributes column for this GFF flavour.
+    fn separator(&self) -> (&'static str, &'static str) {
+        match *self {
+            GffType::GFF3 => (";", "="),
+            GffType::GFF2 | GffType::GTF2 => ("; ", " "),
+        }
+    }
+}
+
+/// Percent-decode a single attribute key or value.
+fn decode_value(value: &str, gff_type: GffType) -> String {
+    if gff_type == GffType::GFF3 {
+        percent_decode(value.as_bytes()).decode_utf8_lossy().into_owned()
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Percent-encode a single attribute value, if needed.
+fn encode_value(value: &str, gff_type: GffType) -> String {
+    if gff_type == GffType::GFF3 && value.chars().any(|c| matches!(c, ';' | '=' | '&' | ',' | '\t' | '\n' | '%')) {
+        percent_encode(value.as_bytes(), GFF3_ATTRIBUTE_ENCODE_SET).to_string()
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Parse the raw attributes column into a multimap.
+fn decode_attributes(column: &str, gff_type: GffType) -> MultiMap<String, String> {
+    let (field_sep, kv_sep) = gff_type.separator();
+    let mut attributes = MultiMap::new();
+    for field in column.split(field_sep) {
+        let field = field.trim_matches(';').trim();
+        if field.is_empty() {
+            continue;
+        }
+        let mut kv = field.splitn(2, kv_sep);
+        let key = decode_value(kv.next().unwrap_or(""), gff_type);
+        let value = kv.next().unwrap_or("").trim_matches('"');
+        if gff_type == GffType::GFF3 {
+            for v in value.split(',') {
+                attributes.insert(key.clone(), decode_value(v, gff_type));
+            }
+        } else {
+            attributes.insert(key.clone(), value.to_owned());
+        }
+    }
+    attributes
+}
+
+/// Serialize a multimap back into a single attributes column.
+fn encode_attributes(attributes: &MultiMap<String, String>, gff_type: GffType) -> String {
+    let (field_sep, kv_sep) = gff_type.separator();
+    let joined = attributes.iter_all().map(|(key, values)| {
+        let value = values.iter().map(|v| encode_value(v, gff_type)).collect::<Vec<_>>().join(",");
+        match gff_type {
+            GffType::GTF2 => format!("{}{}\"{}\"", key, kv_sep, value),
+            _ => format!("{}{}{}", key, kv_sep, value),
+        }
+    }).collect::<Vec<_>>().join(field_sep);
+    // GTF2 terminates every field with a trailing `;`, but that belongs once
+    // at the end of the whole column, not baked into each joined entry
+    // (field_sep is already "; ", so doing both doubles the semicolon).
+    match gff_type {
+        GffType::GTF2 if !joined.is_empty() => format!("{};", joined),
+        _ => joined,
+    }
+}
+
 /// A GFF reader.
 pub struct Reader<R: io::Read> {
     inner: csv::Reader<R>,
+    gff_type: GffType,
 }
 
 impl Reader<fs::File> {
     /// Read GFF from given file path.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        fs::File::open(path).map(Reader::new)
+    pub fn from_file<P: AsRef<Path>>(path: P, fileformat: GffType) -> io::Result<Self> {
+        fs::File::open(path).map(|f| Reader::new(f, fileformat))
     }
 }
 
 
 impl<R: io::Read> Reader<R> {
-    /// Create a new GFF reader given an instance of `io::Read`.
-    pub fn new(reader: R) -> Self {
+    /// Create a new GFF reader given an instance of `io::Read`, using the
+    /// attribute encoding rules of `fileformat`.
+    pub fn new(reader: R, fileformat: GffType) -> Self {
         Reader {
-            inner: csv::Reader::from_reader(reader).delimiter(b'\t').has_headers(false)
+            inner: csv::Reader::from_reader(reader).delimiter(b'\t').has_headers(false),
+            gff_type: fileformat,
         }
     }
 
     /// Iterate over all records.
     pub fn records(&mut self) -> Records<R> {
-        Records { inner: self.inner.decode() }
+        Records { inner: self.inner.decode(), gff_type: self.gff_type }
+    }
+
+    /// Assemble the GFF3 feature hierarchy from `ID`/`Parent` attributes.
+    pub fn feature_graph(&mut self) -> Result<FeatureGraph> {
+        let mut records = Vec::new();
+        for record in self.records() {
+            records.push(try!(record));
+        }
+        FeatureGraph::new(records)
     }
 }
 
 /// A GFF record.
 pub struct Records<'a, R: 'a + io::Read> {
     inner: csv::DecodedRecords<'a, R, (String, String, String, u64, u64, String, String, String, String)>,
+    gff_type: GffType,
 }
 
 
@@ -65,6 +170,7 @@ impl<'a, R: io::Read> Iterator for Records<'a, R> {
     type Item = csv::Result<Record>;
 
     fn next(&mut self) -> Option<csv::Result<Record>> {
+        let gff_type = self.gff_type;
         self.inner.next().map(|res| {
             res.map(|(seqname, source, feature_type, start, end, score, strand, frame, attributes)| {
                 Record {
@@ -76,11 +182,7 @@ impl<'a, R: io::Read> Iterator for Records<'a, R> {
                     score: score,
                     strand: strand,
                     frame: frame,
-                    attributes: csv::Reader::from_string(attributes)
-                        .delimiter(b'=')
-                        .record_terminator(csv::RecordTerminator::Any(b';'))
-                        .has_headers(false)
-                        .decode().collect::<csv::Result<HashMap<String, String>>>().unwrap(),
+                    attributes: decode_attributes(&attributes, gff_type),
                 }
             })
         })
@@ -91,28 +193,30 @@ impl<'a, R: io::Read> Iterator for Records<'a, R> {
 /// A GFF writer.
 pub struct Writer<W: io::Write> {
     inner: csv::Writer<W>,
+    gff_type: GffType,
 }
 
 
 impl Writer<fs::File> {
     /// Write to a given file path.
-    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        fs::File::create(path).map(Writer::new)
+    pub fn to_file<P: AsRef<Path>>(path: P, fileformat: GffType) -> io::Result<Self> {
+        fs::File::create(path).map(|f| Writer::new(f, fileformat))
     }
 }
 
 
 impl<W: io::Write> Writer<W> {
-    /// Write to a given writer.
-    pub fn new(writer: W) -> Self {
-        Writer { inner: csv::Writer::from_writer(writer).delimiter(b'\t').flexible(true) }
+    /// Write to a given writer, using the attribute encoding rules of
+    /// `fileformat`.
+    pub fn new(writer: W, fileformat: GffType) -> Self {
+        Writer { inner: csv::Writer::from_writer(writer).delimiter(b'\t').flexible(true), gff_type: fileformat }
     }
 
     /// Write a given GFF record.
     pub fn write(&mut self, record: Record) -> csv::Result<()> {
         let attributes;
         if !record.attributes.is_empty() {
-            attributes = record.attributes.iter().map(|(a, b)| format!("{}={}", a, b)).collect::<Vec<_>>().join(";");
+            attributes = encode_attributes(&record.attributes, self.gff_type);
         } else {
             attributes = "".to_owned();
         }
@@ -122,7 +226,6 @@ impl<W: io::Write> Writer<W> {
 
 
 /// A GFF record
-#[derive(RustcEncodable)]
 pub struct Record {
     seqname: String,
     source: String,
@@ -132,7 +235,7 @@ pub struct Record {
     score: String,
     strand: String,
     frame: String,
-    attributes: HashMap<String, String>,
+    attributes: MultiMap<String, String>,
 }
 
 impl Record {
@@ -147,7 +250,7 @@ impl Record {
             score: ".".to_owned(),
             strand: ".".to_owned(),
             frame: "".to_owned(),
-            attributes: HashMap::<String, String>::new(),
+            attributes: MultiMap::new(),
         }
     }
 
@@ -198,11 +301,21 @@ impl Record {
         &self.frame
     }
 
-    /// Attribute of feature
-    pub fn attributes(&self) -> &HashMap<String, String> {
+    /// Attributes of feature.
+    pub fn attributes(&self) -> &MultiMap<String, String> {
         &self.attributes
     }
-    
+
+    /// First value of the attribute `key`.
+    pub fn attribute(&self, key: &str) -> Option<&String> {
+        self.attributes.get(key)
+    }
+
+    /// All values of the attribute `key`.
+    pub fn attribute_all(&self, key: &str) -> Option<&Vec<String>> {
+        self.attributes.get_vec(key)
+    }
+
     /// Get mutable reference on seqname of feature.
     pub fn seqname_mut(&mut self) -> &mut String {
         return &mut self.seqname;
@@ -239,25 +352,193 @@ impl Record {
     }
 
     /// Get mutable reference on attributes of feature.
-    pub fn attributes_mut(&mut self) -> &mut HashMap<String, String> {
+    pub fn attributes_mut(&mut self) -> &mut MultiMap<String, String> {
         return &mut self.attributes;
     }
 }
 
+/// An error building a `FeatureGraph` from a set of records.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the underlying GFF failed.
+    Csv(csv::Error),
+    /// A record's `Parent` attribute names an `ID` that no record has.
+    DanglingParent(String),
+    /// The `ID`/`Parent` graph contains a cycle.
+    Cycle(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Csv(ref e) => write!(f, "error reading GFF record: {}", e),
+            Error::DanglingParent(ref id) => write!(f, "Parent references unknown ID '{}'", id),
+            Error::Cycle(ref id) => write!(f, "cycle detected in feature graph at '{}'", id),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Csv(ref e) => e.description(),
+            Error::DanglingParent(..) => "Parent references unknown ID",
+            Error::Cycle(..) => "cycle detected in feature graph",
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Error {
+        Error::Csv(e)
+    }
+}
+
+/// Result of building a `FeatureGraph`.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Cycle-detection state for a node in the `ID`/`Parent` graph.
+enum Mark {
+    Visiting,
+    Visited,
+}
+
+/// The GFF3 feature hierarchy assembled from `ID`/`Parent` attributes.
+pub struct FeatureGraph {
+    records: Vec<Record>,
+    children: HashMap<String, Vec<usize>>,
+    roots: Vec<usize>,
+}
+
+impl FeatureGraph {
+    fn new(records: Vec<Record>) -> Result<FeatureGraph> {
+        let mut ids = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            if let Some(id) = record.attribute("ID") {
+                ids.insert(id.clone(), index);
+            }
+        }
+
+        let mut children: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut roots = Vec::new();
+        for (index, record) in records.iter().enumerate() {
+            match record.attribute_all("Parent") {
+                None => roots.push(index),
+                Some(parents) => {
+                    for parent in parents {
+                        if !ids.contains_key(parent) {
+                            return Err(Error::DanglingParent(parent.clone()));
+                        }
+                        children.entry(parent.clone()).or_insert_with(Vec::new).push(index);
+                    }
+                }
+            }
+        }
+
+        let graph = FeatureGraph { records: records, children: children, roots: roots };
+        try!(graph.check_acyclic());
+        Ok(graph)
+    }
+
+    fn check_acyclic(&self) -> Result<()> {
+        let mut mark = HashMap::new();
+        for index in 0..self.records.len() {
+            if !mark.contains_key(&index) {
+                try!(self.visit(index, &mut mark));
+            }
+        }
+        Ok(())
+    }
+
+    fn visit(&self, index: usize, mark: &mut HashMap<usize, Mark>) -> Result<()> {
+        mark.insert(index, Mark::Visiting);
+        for &child in self.children_of(index) {
+            match mark.get(&child) {
+                Some(&Mark::Visiting) => {
+                    let id = self.records[child].attribute("ID").cloned().unwrap_or_default();
+                    return Err(Error::Cycle(id));
+                }
+                Some(&Mark::Visited) => continue,
+                None => try!(self.visit(child, mark)),
+            }
+        }
+        mark.insert(index, Mark::Visited);
+        Ok(())
+    }
+
+    fn children_of(&self, index: usize) -> &[usize] {
+        match self.records[index].attribute("ID") {
+            Some(id) => self.children.get(id).map(|v| v.as_slice()).unwrap_or(&[]),
+            None => &[],
+        }
+    }
+
+    /// Indices of the features with no `Parent`.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// The record at `index`.
+    pub fn record(&self, index: usize) -> &Record {
+        &self.records[index]
+    }
+
+    /// Indices of the direct children of the feature at `index`.
+    pub fn children(&self, index: usize) -> &[usize] {
+        self.children_of(index)
+    }
+
+    /// Depth-first iterator starting at the feature at `index`.
+    pub fn iter_from(&self, index: usize) -> DepthFirst {
+        DepthFirst { graph: self, stack: vec![index] }
+    }
+}
+
+/// Depth-first iterator returned by `FeatureGraph::iter_from`.
+pub struct DepthFirst<'a> {
+    graph: &'a FeatureGraph,
+    stack: Vec<usize>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a Record;
+
+    fn next(&mut self) -> Option<&'a Record> {
+        let index = match self.stack.pop() {
+            Some(index) => index,
+            None => return None,
+        };
+        for &child in self.graph.children_of(index).iter().rev() {
+            self.stack.push(child);
+        }
+        Some(self.graph.record(index))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use io::Strand;
-    use std::collections::HashMap;
-    
+
     const GFF_FILE: &'static [u8] = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\tNote=Removed;ID=test
 P0A7B8\tUniProtKB\tChain\t2\t176\t50\t+\t.\tNote=ATP-dependent protease subunit HslV;ID=PRO_0000148105
 ";
-    //required because HashMap iter on element randomly
     const GFF_FILE_NO_ATTRIB: &'static [u8] = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\t
 P0A7B8\tUniProtKB\tChain\t2\t176\t50\t+\t.\t
 ";
 
+    const GTF_FILE: &'static [u8] = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\tgene_id \"test\"; note \"Removed\";
+";
+
+    const GFF_FILE_MULTI_PARENT: &'static [u8] = b"P0A7B8\tUniProtKB\tExon\t1\t1\t.\t.\t.\tID=exon1;Parent=mRNA1,mRNA2
+";
+
+    const GFF2_FILE_QUOTED: &'static [u8] = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\tgene_id \"test\"; note \"Removed\";
+";
+
+    const GFF2_FILE_UNQUOTED: &'static [u8] = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\tgene_id test; note Removed;
+";
+
     #[test]
     fn test_reader() {
         let seqname = ["P0A7B8", "P0A7B8"];
@@ -268,13 +549,10 @@ P0A7B8\tUniProtKB\tChain\t2\t176\t50\t+\t.\t
         let scores = [None, Some(50)];
         let strand = [None, Some(Strand::Forward)];
         let frame = [".", "."];
-        let mut attributes = [HashMap::new(), HashMap::new()];
-        attributes[0].insert("ID".to_owned(), "test".to_owned());
-        attributes[0].insert("Note".to_owned(), "Removed".to_owned());
-        attributes[1].insert("ID".to_owned(), "PRO_0000148105".to_owned());
-        attributes[1].insert("Note".to_owned(), "ATP-dependent protease subunit HslV".to_owned());
+        let ids = ["test", "PRO_0000148105"];
+        let notes = ["Removed", "ATP-dependent protease subunit HslV"];
 
-        let mut reader = Reader::new(GFF_FILE);
+        let mut reader = Reader::new(GFF_FILE, GffType::GFF3);
         for (i, r) in reader.records().enumerate() {
             let record = r.ok().expect("Error reading record");
             assert_eq!(record.seqname(), seqname[i]);
@@ -285,17 +563,168 @@ P0A7B8\tUniProtKB\tChain\t2\t176\t50\t+\t.\t
             assert_eq!(record.score(), scores[i]);
             assert_eq!(record.strand(), strand[i]);
             assert_eq!(record.frame(), frame[i]);
-            assert_eq!(record.attributes(), &attributes[i]);
+            assert_eq!(record.attribute("ID").map(|s| s.as_str()), Some(ids[i]));
+            assert_eq!(record.attribute("Note").map(|s| s.as_str()), Some(notes[i]));
+        }
+    }
+
+    #[test]
+    fn test_reader_gtf2() {
+        let mut reader = Reader::new(GTF_FILE, GffType::GTF2);
+        let record = reader.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("gene_id").map(|s| s.as_str()), Some("test"));
+        assert_eq!(record.attribute("note").map(|s| s.as_str()), Some("Removed"));
+    }
+
+    #[test]
+    fn test_reader_gtf2_does_not_split_literal_comma() {
+        let gff = b"P0A7B8\tUniProtKB\tInitiator methionine\t1\t1\t.\t.\t.\tgene_id \"test\"; note \"Highly conserved, essential gene\";\n";
+        let mut reader = Reader::new(&gff[..], GffType::GTF2);
+        let record = reader.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("note").map(|s| s.as_str()), Some("Highly conserved, essential gene"));
+        assert_eq!(record.attribute_all("note"), Some(&vec!["Highly conserved, essential gene".to_owned()]));
+    }
+
+    #[test]
+    fn test_writer_gtf2_does_not_double_semicolons() {
+        let mut reader = Reader::new(GTF_FILE, GffType::GTF2);
+        let mut writer = Writer::new(vec![], GffType::GTF2);
+        for r in reader.records() {
+            writer.write(r.ok().expect("Error reading record")).ok().expect("Error writing record");
+        }
+
+        let written = writer.inner.as_string().to_owned();
+        assert!(!written.contains(";;"), "doubled semicolon in GTF2 attributes: {}", written);
+
+        let written_bytes = written.into_bytes();
+        let mut roundtripped = Reader::new(&written_bytes[..], GffType::GTF2);
+        let record = roundtripped.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("gene_id").map(|s| s.as_str()), Some("test"));
+        assert_eq!(record.attribute("note").map(|s| s.as_str()), Some("Removed"));
+    }
+
+    #[test]
+    fn test_reader_gff2() {
+        for gff in &[GFF2_FILE_QUOTED, GFF2_FILE_UNQUOTED] {
+            let mut reader = Reader::new(*gff, GffType::GFF2);
+            let record = reader.records().next().unwrap().ok().expect("Error reading record");
+            assert_eq!(record.attribute("gene_id").map(|s| s.as_str()), Some("test"));
+            assert_eq!(record.attribute("note").map(|s| s.as_str()), Some("Removed"));
+        }
+    }
+
+    #[test]
+    fn test_writer_gff2_does_not_quote_values() {
+        let mut reader = Reader::new(GFF2_FILE_UNQUOTED, GffType::GFF2);
+        let mut writer = Writer::new(vec![], GffType::GFF2);
+        for r in reader.records() {
+            writer.write(r.ok().expect("Error reading record")).ok().expect("Error writing record");
         }
+
+        let written = writer.inner.as_string().to_owned();
+        assert!(!written.contains('"'), "GFF2 values should not be quoted: {}", written);
+
+        let written_bytes = written.into_bytes();
+        let mut roundtripped = Reader::new(&written_bytes[..], GffType::GFF2);
+        let record = roundtripped.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("gene_id").map(|s| s.as_str()), Some("test"));
+        assert_eq!(record.attribute("note").map(|s| s.as_str()), Some("Removed"));
+    }
+
+    #[test]
+    fn test_reader_multi_valued_parent() {
+        let mut reader = Reader::new(GFF_FILE_MULTI_PARENT, GffType::GFF3);
+        let record = reader.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute_all("Parent"), Some(&vec!["mRNA1".to_owned(), "mRNA2".to_owned()]));
+    }
+
+    #[test]
+    fn test_reader_percent_decodes_reserved_characters() {
+        let gff = b"P0A7B8\tUniProtKB\tExon\t1\t1\t.\t.\t.\tNote=semicolon%3Bcomma%2Cvalue\n";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        let record = reader.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("Note").map(|s| s.as_str()), Some("semicolon;comma,value"));
+    }
+
+    #[test]
+    fn test_reader_writer_roundtrip_with_reserved_characters() {
+        let gff = b"P0A7B8\tUniProtKB\tExon\t1\t1\t.\t.\t.\tNote=semicolon%3Bcomma%2Cvalue\n";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        let mut writer = Writer::new(vec![], GffType::GFF3);
+        for r in reader.records() {
+            writer.write(r.ok().expect("Error reading record")).ok().expect("Error writing record");
+        }
+
+        let written = writer.inner.as_string().to_owned().into_bytes();
+        let mut roundtripped = Reader::new(&written[..], GffType::GFF3);
+        let record = roundtripped.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("Note").map(|s| s.as_str()), Some("semicolon;comma,value"));
+    }
+
+    #[test]
+    fn test_reader_writer_roundtrip_with_literal_percent() {
+        let mut writer = Writer::new(vec![], GffType::GFF3);
+        let mut record = Record::new();
+        record.attributes_mut().insert("Note".to_owned(), "Error code %3D not found".to_owned());
+        writer.write(record).ok().expect("Error writing record");
+
+        let written = writer.inner.as_string().to_owned().into_bytes();
+        let mut roundtripped = Reader::new(&written[..], GffType::GFF3);
+        let record = roundtripped.records().next().unwrap().ok().expect("Error reading record");
+        assert_eq!(record.attribute("Note").map(|s| s.as_str()), Some("Error code %3D not found"));
     }
 
     #[test]
     fn test_writer() {
-        let mut reader = Reader::new(GFF_FILE_NO_ATTRIB);
-        let mut writer = Writer::new(vec![]);
+        let mut reader = Reader::new(GFF_FILE_NO_ATTRIB, GffType::GFF3);
+        let mut writer = Writer::new(vec![], GffType::GFF3);
         for r in reader.records() {
             writer.write(r.ok().expect("Error reading record")).ok().expect("Error writing record");
         }
         assert_eq!(writer.inner.as_string(), String::from_utf8_lossy(GFF_FILE_NO_ATTRIB))
     }
+
+    const GFF_FILE_FEATURE_TREE: &'static [u8] =
+        b"chr1\tsrc\tgene\t1\t1000\t.\t+\t.\tID=gene1
+chr1\tsrc\tmRNA\t1\t1000\t.\t+\t.\tID=mRNA1;Parent=gene1
+chr1\tsrc\texon\t1\t500\t.\t+\t.\tID=exon1;Parent=mRNA1
+chr1\tsrc\texon\t501\t1000\t.\t+\t.\tID=exon2;Parent=mRNA1
+";
+
+    #[test]
+    fn test_feature_graph() {
+        let mut reader = Reader::new(GFF_FILE_FEATURE_TREE, GffType::GFF3);
+        let graph = reader.feature_graph().ok().expect("Error building feature graph");
+
+        assert_eq!(graph.roots().len(), 1);
+        let gene = graph.roots()[0];
+        assert_eq!(graph.record(gene).attribute("ID").map(|s| s.as_str()), Some("gene1"));
+
+        let ids: Vec<_> = graph.iter_from(gene)
+            .map(|r| r.attribute("ID").cloned().unwrap_or_default())
+            .collect();
+        assert_eq!(ids, vec!["gene1", "mRNA1", "exon1", "exon2"]);
+    }
+
+    #[test]
+    fn test_feature_graph_dangling_parent() {
+        let gff = b"chr1\tsrc\texon\t1\t500\t.\t+\t.\tID=exon1;Parent=mRNA1\n";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        match reader.feature_graph() {
+            Err(Error::DanglingParent(ref id)) => assert_eq!(id, "mRNA1"),
+            other => panic!("expected a dangling Parent error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_feature_graph_cycle() {
+        let gff = b"chr1\tsrc\tmRNA\t1\t1000\t.\t+\t.\tID=a;Parent=b
+chr1\tsrc\tmRNA\t1\t1000\t.\t+\t.\tID=b;Parent=a
+";
+        let mut reader = Reader::new(&gff[..], GffType::GFF3);
+        match reader.feature_graph() {
+            Err(Error::Cycle(..)) => {}
+            other => panic!("expected a cycle error, got {:?}", other.err()),
+        }
+    }
 }